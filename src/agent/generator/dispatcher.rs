@@ -0,0 +1,376 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use rand::Rng;
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+
+use super::{ChatOptions, Client, RetryDecision, BASE_BACKOFF};
+use crate::agent::{state::SharedState, Invocation};
+
+// items awaiting their scheduled retry time, kept ordered by `ready_at` so
+// the earliest-scheduled item is always `front()` regardless of the order
+// items were requeued in.
+struct ReadyQueue<T> {
+    items: VecDeque<(Instant, T)>,
+}
+
+impl<T> ReadyQueue<T> {
+    fn new() -> Self {
+        Self {
+            items: VecDeque::new(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    // inserts `item` keeping the queue sorted by `ready_at`.
+    fn insert(&mut self, ready_at: Instant, item: T) {
+        let pos = self
+            .items
+            .iter()
+            .position(|(t, _)| *t > ready_at)
+            .unwrap_or(self.items.len());
+        self.items.insert(pos, (ready_at, item));
+    }
+
+    // how long until the earliest item becomes ready, if any.
+    fn next_wake(&self, now: Instant) -> Option<Duration> {
+        self.items
+            .front()
+            .map(|(ready_at, _)| ready_at.saturating_duration_since(now))
+    }
+
+    // removes and returns the earliest item if it's ready by `now`.
+    fn pop_ready(&mut self, now: Instant) -> Option<T> {
+        match self.items.front() {
+            Some((ready_at, _)) if *ready_at <= now => self.items.pop_front().map(|(_, item)| item),
+            _ => None,
+        }
+    }
+}
+
+// default number of retries a work item gets after its first attempt before
+// it's reported as failed, mirroring `MAX_RETRIES` in the retry loop for
+// single chat calls.
+const DEFAULT_MAX_RETRIES: u32 = 5;
+
+// a single chat call submitted to a `Dispatcher`, with its own retry budget
+// independent from every other item in flight.
+pub struct WorkItem {
+    pub client: Arc<dyn Client>,
+    pub state: SharedState,
+    pub options: ChatOptions,
+    attempt: u32,
+    max_retries: u32,
+}
+
+impl WorkItem {
+    pub fn new(client: Arc<dyn Client>, state: SharedState, options: ChatOptions) -> Self {
+        Self {
+            client,
+            state,
+            options,
+            attempt: 0,
+            max_retries: DEFAULT_MAX_RETRIES,
+        }
+    }
+
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+}
+
+// point-in-time snapshot of `Dispatcher` throughput, handed back to callers
+// that want to observe progress without touching the atomics directly.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DispatcherStats {
+    pub pending: usize,
+    pub in_flight: usize,
+    pub failed: usize,
+}
+
+#[derive(Default)]
+struct Counters {
+    pending: AtomicUsize,
+    in_flight: AtomicUsize,
+    failed: AtomicUsize,
+}
+
+// dispatches `WorkItem`s with bounded concurrency: at most `capacity` chat
+// calls run at once, producers are back-pressured by the bounded mpsc
+// channel, and a failing item is retried on its own schedule (per
+// `Client::classify_error`) instead of blocking the rest of the batch.
+pub struct Dispatcher {
+    submit: mpsc::Sender<WorkItem>,
+    counters: Arc<Counters>,
+}
+
+impl Dispatcher {
+    // spawns the dispatch loop and returns a handle to submit work plus a
+    // receiver for completed (or permanently failed) results.
+    pub fn spawn(capacity: usize) -> (Self, mpsc::Receiver<Result<(String, Vec<Invocation>)>>) {
+        // `mpsc::channel` panics on a zero capacity; a dispatcher with no
+        // concurrency at all doesn't make sense, so treat it as 1.
+        let capacity = capacity.max(1);
+        let (submit_tx, submit_rx) = mpsc::channel(capacity);
+        let (result_tx, result_rx) = mpsc::channel(capacity);
+        let counters = Arc::new(Counters::default());
+
+        tokio::spawn(Self::run(capacity, submit_rx, result_tx, counters.clone()));
+
+        (
+            Self {
+                submit: submit_tx,
+                counters,
+            },
+            result_rx,
+        )
+    }
+
+    pub async fn submit(&self, item: WorkItem) -> Result<()> {
+        self.counters.pending.fetch_add(1, Ordering::SeqCst);
+        self.submit
+            .send(item)
+            .await
+            .map_err(|_| anyhow::anyhow!("dispatcher is no longer accepting work"))
+    }
+
+    pub fn stats(&self) -> DispatcherStats {
+        DispatcherStats {
+            pending: self.counters.pending.load(Ordering::SeqCst),
+            in_flight: self.counters.in_flight.load(Ordering::SeqCst),
+            failed: self.counters.failed.load(Ordering::SeqCst),
+        }
+    }
+
+    async fn run(
+        capacity: usize,
+        mut submit_rx: mpsc::Receiver<WorkItem>,
+        result_tx: mpsc::Sender<Result<(String, Vec<Invocation>)>>,
+        counters: Arc<Counters>,
+    ) {
+        let mut in_flight: JoinSet<(WorkItem, Result<(String, Vec<Invocation>)>)> = JoinSet::new();
+        // items waiting for their scheduled retry time before they're
+        // eligible to be spawned again.
+        let mut waiting: ReadyQueue<WorkItem> = ReadyQueue::new();
+        let mut closed = false;
+
+        loop {
+            // promote ready items back into flight, but never past
+            // `capacity`.
+            while in_flight.len() < capacity {
+                match waiting.pop_ready(Instant::now()) {
+                    Some(item) => Self::spawn_item(&mut in_flight, item, &counters),
+                    None => break,
+                }
+            }
+
+            if closed && waiting.is_empty() && in_flight.is_empty() {
+                break;
+            }
+
+            if in_flight.len() >= capacity && !in_flight.is_empty() {
+                // at capacity: the only way to make progress is to drain a
+                // completed future before accepting more work.
+                if let Some(joined) = in_flight.join_next().await {
+                    Self::handle_completion(joined, &mut waiting, &result_tx, &counters).await;
+                }
+                continue;
+            }
+
+            // how long until the earliest waiting item's backoff elapses,
+            // if any. without this, a lone item sitting in `waiting` with
+            // nothing in flight and nothing left to submit would never
+            // wake the loop up again.
+            let next_wake = waiting.next_wake(Instant::now());
+
+            tokio::select! {
+                item = submit_rx.recv(), if !closed => {
+                    match item {
+                        Some(item) => Self::spawn_item(&mut in_flight, item, &counters),
+                        None => closed = true,
+                    }
+                }
+                joined = in_flight.join_next(), if !in_flight.is_empty() => {
+                    if let Some(joined) = joined {
+                        Self::handle_completion(joined, &mut waiting, &result_tx, &counters).await;
+                    }
+                }
+                _ = tokio::time::sleep(next_wake.unwrap_or_default()), if next_wake.is_some() => {
+                    // nothing to do here: looping back around promotes any
+                    // item whose backoff just elapsed.
+                }
+            }
+        }
+    }
+
+    fn spawn_item(
+        in_flight: &mut JoinSet<(WorkItem, Result<(String, Vec<Invocation>)>)>,
+        item: WorkItem,
+        counters: &Arc<Counters>,
+    ) {
+        counters.pending.fetch_sub(1, Ordering::SeqCst);
+        counters.in_flight.fetch_add(1, Ordering::SeqCst);
+
+        in_flight.spawn(async move {
+            let result = item.client.chat(item.state.clone(), &item.options).await;
+            (item, result)
+        });
+    }
+
+    async fn handle_completion(
+        joined: Result<(WorkItem, Result<(String, Vec<Invocation>)>), tokio::task::JoinError>,
+        waiting: &mut ReadyQueue<WorkItem>,
+        result_tx: &mpsc::Sender<Result<(String, Vec<Invocation>)>>,
+        counters: &Arc<Counters>,
+    ) {
+        counters.in_flight.fetch_sub(1, Ordering::SeqCst);
+
+        let (mut item, result) = match joined {
+            Ok(pair) => pair,
+            Err(err) => {
+                counters.failed.fetch_add(1, Ordering::SeqCst);
+                let _ = result_tx.send(Err(anyhow::anyhow!(err))).await;
+                return;
+            }
+        };
+
+        let error = match result {
+            Ok(ok) => {
+                let _ = result_tx.send(Ok(ok)).await;
+                return;
+            }
+            Err(error) => error,
+        };
+
+        let decision = item.client.classify_error(&error.to_string());
+        let delay = match decision {
+            RetryDecision::Fatal => None,
+            RetryDecision::RetryAfter(delay) => Some(delay),
+            RetryDecision::Backoff => Some(backoff_delay(item.attempt)),
+        };
+
+        item.attempt += 1;
+
+        if let Some(delay) = delay {
+            if item.attempt <= item.max_retries {
+                counters.pending.fetch_add(1, Ordering::SeqCst);
+                waiting.insert(Instant::now() + delay, item);
+                return;
+            }
+        }
+
+        counters.failed.fetch_add(1, Ordering::SeqCst);
+        let _ = result_tx.send(Err(error)).await;
+    }
+}
+
+// full-jitter exponential backoff for a `RetryDecision::Backoff`: a random
+// delay in `[0, BASE_BACKOFF * 2^attempt]`, matching `chat_with_retry`.
+fn backoff_delay(attempt: u32) -> Duration {
+    let backoff = BASE_BACKOFF * 2u32.saturating_pow(attempt.min(16));
+    Duration::from_secs_f64(rand::thread_rng().gen_range(0.0..=backoff.as_secs_f64()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_delay_never_exceeds_the_exponential_cap() {
+        for attempt in 0..8 {
+            let cap = BASE_BACKOFF * 2u32.saturating_pow(attempt);
+            for _ in 0..50 {
+                let delay = backoff_delay(attempt);
+                assert!(delay <= cap, "attempt {}: {:?} > cap {:?}", attempt, delay, cap);
+            }
+        }
+    }
+
+    #[test]
+    fn backoff_delay_cap_grows_with_attempt() {
+        let small_cap = BASE_BACKOFF * 2u32.saturating_pow(0);
+        let large_cap = BASE_BACKOFF * 2u32.saturating_pow(5);
+        assert!(large_cap > small_cap);
+    }
+
+    // `mini_rag::Embedder`'s exact interface lives outside this tree
+    // snapshot, so a fake `Client` can't be constructed here to drive the
+    // real `Dispatcher` end to end. `ReadyQueue` holds the two invariants
+    // that were actually broken (ordering by `ready_at`, capacity-gated
+    // promotion) and doesn't depend on `Client`/`SharedState`, so it's
+    // exercised directly instead.
+
+    #[test]
+    fn ready_queue_pop_ready_returns_earliest_deadline_first() {
+        let now = Instant::now();
+        let mut queue = ReadyQueue::new();
+
+        // insert the later-ready item first to prove ordering isn't
+        // governed by insertion order.
+        queue.insert(now + Duration::from_secs(10), "late");
+        queue.insert(now + Duration::from_secs(1), "early");
+
+        let far_future = now + Duration::from_secs(100);
+        assert_eq!(queue.pop_ready(far_future), Some("early"));
+        assert_eq!(queue.pop_ready(far_future), Some("late"));
+        assert_eq!(queue.pop_ready(far_future), None);
+    }
+
+    #[test]
+    fn ready_queue_pop_ready_honors_the_scheduled_wake_time() {
+        let now = Instant::now();
+        let mut queue = ReadyQueue::new();
+        queue.insert(now + Duration::from_secs(10), "not yet");
+
+        assert_eq!(queue.pop_ready(now), None);
+        assert_eq!(queue.next_wake(now), Some(Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn ready_queue_next_wake_tracks_the_earliest_item() {
+        let now = Instant::now();
+        let mut queue: ReadyQueue<&str> = ReadyQueue::new();
+        assert_eq!(queue.next_wake(now), None);
+
+        queue.insert(now + Duration::from_secs(5), "later");
+        queue.insert(now + Duration::from_secs(2), "sooner");
+
+        assert_eq!(queue.next_wake(now), Some(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn promotion_never_exceeds_capacity() {
+        let now = Instant::now();
+        let mut queue = ReadyQueue::new();
+        for i in 0..5 {
+            queue.insert(now, i);
+        }
+
+        let capacity = 2;
+        let mut in_flight = 0;
+        let mut promoted = Vec::new();
+
+        // the same capacity-gated promotion loop `Dispatcher::run` uses.
+        while in_flight < capacity {
+            match queue.pop_ready(now) {
+                Some(item) => {
+                    promoted.push(item);
+                    in_flight += 1;
+                }
+                None => break,
+            }
+        }
+
+        assert_eq!(promoted, vec![0, 1]);
+        assert_eq!(in_flight, capacity);
+        assert!(!queue.is_empty(), "remaining items must stay queued, not over-spawned");
+    }
+}
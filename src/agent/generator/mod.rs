@@ -4,6 +4,7 @@ use anyhow::Result;
 use async_trait::async_trait;
 use duration_string::DurationString;
 use lazy_static::lazy_static;
+use rand::Rng;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 
@@ -20,14 +21,87 @@ mod ollama;
 #[cfg(feature = "openai")]
 mod openai;
 
+mod dispatcher;
 mod options;
+mod session;
+mod spec;
 
+pub use dispatcher::*;
 pub use options::*;
+pub use session::*;
+pub use spec::*;
 
 lazy_static! {
     static ref RETRY_TIME_PARSER: Regex =
         Regex::new(r"(?m)^.+try again in (.+)\. Visit.*").unwrap();
     static ref CONN_RESET_PARSER: Regex = Regex::new(r"(?m)^.+onnection reset by peer.*").unwrap();
+    static ref FATAL_ERROR_PARSER: Regex =
+        Regex::new(r"(?i)(unauthorized|invalid api key|invalid_request_error|\b40[13]\b)").unwrap();
+}
+
+// retries are capped so that a persistently failing backend can't hang the
+// caller forever.
+const MAX_RETRIES: u32 = 5;
+// base delay for the exponential backoff used for `RetryDecision::Backoff`.
+const BASE_BACKOFF: Duration = Duration::from_millis(500);
+
+// outcome of `Client::classify_error`: whether a failed call is worth
+// retrying, and if so how long to wait before trying again.
+#[derive(Clone, Copy, Debug)]
+pub enum RetryDecision {
+    // not worth retrying, e.g. an auth error or a malformed request.
+    Fatal,
+    // the server told us exactly how long to wait.
+    RetryAfter(Duration),
+    // a transient failure (e.g. connection reset): back off and retry.
+    Backoff,
+}
+
+// runs `chat` against `client`, transparently retrying on recoverable
+// errors with exponential backoff and full jitter, honoring any
+// server-provided retry-after delay, and giving up immediately on fatal
+// errors or once `MAX_RETRIES` is exceeded.
+pub async fn chat_with_retry(
+    client: &dyn Client,
+    state: SharedState,
+    options: &ChatOptions,
+) -> Result<(String, Vec<Invocation>)> {
+    let mut attempt: u32 = 0;
+
+    loop {
+        match client.chat(state.clone(), options).await {
+            Ok(result) => return Ok(result),
+            Err(error) => {
+                let decision = client.classify_error(&error.to_string());
+
+                let delay = match decision {
+                    RetryDecision::Fatal => return Err(error),
+                    RetryDecision::RetryAfter(delay) => delay,
+                    RetryDecision::Backoff => {
+                        let backoff = BASE_BACKOFF * 2u32.saturating_pow(attempt);
+                        Duration::from_secs_f64(
+                            rand::thread_rng().gen_range(0.0..=backoff.as_secs_f64()),
+                        )
+                    }
+                };
+
+                if attempt >= MAX_RETRIES {
+                    return Err(error);
+                }
+                attempt += 1;
+
+                log::warn!(
+                    "chat call failed ({}), retrying in {:?} (attempt {}/{}) ...",
+                    error,
+                    delay,
+                    attempt,
+                    MAX_RETRIES,
+                );
+
+                tokio::time::sleep(delay).await;
+            }
+        }
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -35,6 +109,10 @@ pub struct ChatOptions {
     pub system_prompt: String,
     pub prompt: String,
     pub history: Vec<Message>,
+    // free-form per-model generation parameters (e.g. "temperature",
+    // "top_p") threaded through from a `GeneratorSpec` query string.
+    #[serde(default)]
+    pub generation_params: std::collections::HashMap<String, String>,
 }
 
 impl ChatOptions {
@@ -43,8 +121,17 @@ impl ChatOptions {
             system_prompt,
             prompt,
             history,
+            generation_params: std::collections::HashMap::new(),
         }
     }
+
+    pub fn with_generation_params(
+        mut self,
+        generation_params: std::collections::HashMap<String, String>,
+    ) -> Self {
+        self.generation_params = generation_params;
+        self
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -66,6 +153,17 @@ impl Display for Message {
     }
 }
 
+// Callback interface for consumers that want to react to a completion as it
+// is generated instead of waiting for `Client::chat` to return in full.
+pub trait ReplyHandler: Send {
+    // called every time a new chunk of text is produced by the model.
+    fn on_text(&mut self, delta: &str);
+
+    // called when a tool call has been parsed, possibly before the full
+    // completion (and therefore the other tool calls) is available.
+    fn on_tool_call(&mut self, partial: &Invocation);
+}
+
 #[async_trait]
 pub trait Client: mini_rag::Embedder + Send + Sync {
     fn new(url: &str, port: u16, model_name: &str, context_window: u32) -> Result<Self>
@@ -78,12 +176,40 @@ pub trait Client: mini_rag::Embedder + Send + Sync {
         options: &ChatOptions,
     ) -> Result<(String, Vec<Invocation>)>;
 
+    // streaming counterpart of `chat`: providers that can consume a chunked
+    // event stream from their backend should override this and forward each
+    // delta/tool call to `handler` as it arrives. the default implementation
+    // falls back to a single blocking `chat` call and emits the whole reply
+    // as one final `on_text`.
+    async fn chat_stream(
+        &self,
+        state: SharedState,
+        options: &ChatOptions,
+        handler: &mut dyn ReplyHandler,
+    ) -> Result<(String, Vec<Invocation>)> {
+        let (text, invocations) = self.chat(state, options).await?;
+
+        handler.on_text(&text);
+        for inv in &invocations {
+            handler.on_tool_call(inv);
+        }
+
+        Ok((text, invocations))
+    }
+
     async fn check_tools_support(&self) -> Result<bool> {
         Ok(false)
     }
 
-    async fn check_rate_limit(&self, error: &str) -> bool {
-        // if rate limit exceeded, parse the retry time and retry
+    // classifies a chat error as fatal, retryable with a server-provided
+    // delay, or retryable with a generic backoff. used by `chat_with_retry`
+    // to decide whether and how long to wait before trying again.
+    fn classify_error(&self, error: &str) -> RetryDecision {
+        if FATAL_ERROR_PARSER.captures_iter(error).next().is_some() {
+            return RetryDecision::Fatal;
+        }
+
+        // if rate limit exceeded, parse the server provided retry time
         if let Some(caps) = RETRY_TIME_PARSER.captures_iter(error).next() {
             if caps.len() == 2 {
                 let mut retry_time_str = "".to_string();
@@ -100,17 +226,9 @@ pub trait Client: mini_rag::Embedder + Send + Sync {
                 }
 
                 if let Ok(retry_time) = retry_time_str.parse::<DurationString>() {
-                    log::warn!(
-                        "rate limit reached for this model, retrying in {} ...",
-                        retry_time,
-                    );
-
-                    tokio::time::sleep(
+                    return RetryDecision::RetryAfter(
                         retry_time.checked_add(Duration::from_millis(1000)).unwrap(),
-                    )
-                    .await;
-
-                    return true;
+                    );
                 } else {
                     log::error!("can't parse '{}'", &retry_time_str);
                 }
@@ -118,18 +236,10 @@ pub trait Client: mini_rag::Embedder + Send + Sync {
                 log::error!("cap len wrong");
             }
         } else if CONN_RESET_PARSER.captures_iter(error).next().is_some() {
-            let retry_time = Duration::from_secs(5);
-            log::warn!(
-                "connection reset by peer, retrying in {:?} ...",
-                &retry_time,
-            );
-
-            tokio::time::sleep(retry_time).await;
-
-            return true;
+            return RetryDecision::Backoff;
         }
 
-        return false;
+        RetryDecision::Backoff
     }
 }
 
@@ -196,3 +306,30 @@ pub fn factory_embedder(
 ) -> Result<Box<dyn mini_rag::Embedder>> {
     factory_body!(name, url, port, model_name, context_window)
 }
+
+// same as `factory`, but taking a parsed `GeneratorSpec` (e.g. from
+// `openai://gpt-4o@api.example.com:443?ctx=128000&temperature=0.2`) instead
+// of separate positional arguments, so a single config line can fully
+// describe a custom or self-hosted backend. the spec's extra query
+// parameters (temperature, top_p, ...) aren't consumed here since they're
+// per-call, not per-client -- build `ChatOptions` via `spec.chat_options`
+// so they reach `generation_params`.
+pub fn factory_from_spec(spec: &GeneratorSpec) -> Result<Box<dyn Client>> {
+    factory(
+        &spec.provider,
+        &spec.host,
+        spec.port,
+        &spec.model,
+        spec.context_window,
+    )
+}
+
+pub fn factory_embedder_from_spec(spec: &GeneratorSpec) -> Result<Box<dyn mini_rag::Embedder>> {
+    factory_embedder(
+        &spec.provider,
+        &spec.host,
+        spec.port,
+        &spec.model,
+        spec.context_window,
+    )
+}
@@ -0,0 +1,147 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use super::{ChatOptions, Message};
+use crate::agent::task::Task;
+
+// persists the full conversation state (`system_prompt`, `prompt` and
+// `history`, including every `Invocation`) to a compact CBOR file, so a
+// long-running agent can be paused and later resumed from an exact prior
+// state instead of starting over.
+//
+// the write is atomic: the encoded session is first written to a sibling
+// `.tmp` file and then renamed into place, so a crash mid-write never
+// leaves a corrupted checkpoint behind.
+pub fn save_session(path: impl AsRef<Path>, options: &ChatOptions) -> Result<()> {
+    let path = path.as_ref();
+    let tmp_path = path.with_extension("tmp");
+
+    let mut bytes = Vec::new();
+    ciborium::into_writer(options, &mut bytes).context("failed to encode session to CBOR")?;
+
+    let mut file = fs::File::create(&tmp_path)
+        .with_context(|| format!("failed to create temp session file '{}'", tmp_path.display()))?;
+    file.write_all(&bytes)?;
+    file.sync_all()?;
+    drop(file);
+
+    fs::rename(&tmp_path, path).with_context(|| {
+        format!(
+            "failed to move temp session file into place at '{}'",
+            path.display()
+        )
+    })?;
+
+    Ok(())
+}
+
+// loads a session previously written by `save_session`, without applying
+// any history windowing. use `load_session_for_task` to additionally honor
+// a `Task::max_history_visibility`.
+pub fn load_session(path: impl AsRef<Path>) -> Result<ChatOptions> {
+    let path = path.as_ref();
+    let bytes = fs::read(path)
+        .with_context(|| format!("failed to read session file '{}'", path.display()))?;
+
+    ciborium::from_reader(bytes.as_slice()).context("failed to decode session from CBOR")
+}
+
+// loads a session and trims `history` down to `task.max_history_visibility()`
+// most recent entries, so a restored session never exceeds the window the
+// live agent would have enforced.
+pub fn load_session_for_task(path: impl AsRef<Path>, task: &dyn Task) -> Result<ChatOptions> {
+    let mut options = load_session(path)?;
+    trim_history(&mut options.history, task.max_history_visibility() as usize);
+    Ok(options)
+}
+
+// drops the oldest entries of `history` until at most `limit` remain.
+fn trim_history(history: &mut Vec<Message>, limit: usize) {
+    if history.len() > limit {
+        let overflow = history.len() - limit;
+        history.drain(0..overflow);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(n: usize) -> Message {
+        Message::Agent(format!("turn {}", n), None)
+    }
+
+    #[test]
+    fn save_and_load_session_round_trips() {
+        let dir = std::env::temp_dir().join(format!(
+            "nerve-session-test-{}-{}",
+            std::process::id(),
+            "round-trip"
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("session.cbor");
+
+        let options = ChatOptions::new(
+            "system prompt".to_string(),
+            "do the thing".to_string(),
+            vec![message(0), message(1)],
+        );
+
+        save_session(&path, &options).unwrap();
+        let loaded = load_session(&path).unwrap();
+
+        assert_eq!(loaded.system_prompt, options.system_prompt);
+        assert_eq!(loaded.prompt, options.prompt);
+        assert_eq!(loaded.history.len(), options.history.len());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn save_session_leaves_no_temp_file_behind() {
+        let dir = std::env::temp_dir().join(format!(
+            "nerve-session-test-{}-{}",
+            std::process::id(),
+            "atomic"
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("session.cbor");
+
+        let options = ChatOptions::new("s".to_string(), "p".to_string(), Vec::new());
+        save_session(&path, &options).unwrap();
+
+        assert!(path.exists());
+        assert!(!path.with_extension("tmp").exists());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn load_session_on_missing_file_errors() {
+        let path = std::env::temp_dir().join("nerve-session-test-does-not-exist.cbor");
+        assert!(load_session(&path).is_err());
+    }
+
+    #[test]
+    fn trim_history_drops_oldest_entries_first() {
+        let mut history: Vec<Message> = (0..10).map(message).collect();
+        trim_history(&mut history, 3);
+
+        assert_eq!(history.len(), 3);
+        match &history[0] {
+            Message::Agent(data, _) => assert_eq!(data, "turn 7"),
+            _ => panic!("expected an agent message"),
+        }
+    }
+
+    #[test]
+    fn trim_history_is_a_no_op_under_the_limit() {
+        let mut history: Vec<Message> = (0..3).map(message).collect();
+        trim_history(&mut history, 10);
+
+        assert_eq!(history.len(), 3);
+    }
+}
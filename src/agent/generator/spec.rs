@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+
+use super::{ChatOptions, Message};
+
+const DEFAULT_PORT: u16 = 443;
+const DEFAULT_CONTEXT_WINDOW: u32 = 4096;
+
+// a generator described as a single URI-like string, e.g.:
+//
+//   openai://gpt-4o@api.example.com:443?ctx=128000&temperature=0.2
+//
+// instead of separate `name`/`url`/`port`/`model_name`/`context_window`
+// arguments. lets one config line fully describe a backend, including
+// self-hosted or third-party OpenAI-compatible gateways, plus any
+// per-model sampling parameters to thread into `ChatOptions`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GeneratorSpec {
+    pub provider: String,
+    pub model: String,
+    pub host: String,
+    pub port: u16,
+    pub context_window: u32,
+    // every other query parameter (temperature, top_p, ...), passed
+    // through verbatim for the caller to attach to `ChatOptions`.
+    pub params: HashMap<String, String>,
+}
+
+impl GeneratorSpec {
+    pub fn parse(spec: &str) -> Result<Self> {
+        let (provider, rest) = spec
+            .split_once("://")
+            .ok_or_else(|| anyhow!("generator spec '{}' is missing a '://' scheme", spec))?;
+
+        if provider.is_empty() {
+            return Err(anyhow!("generator spec '{}' has an empty provider", spec));
+        }
+
+        let (authority, query) = match rest.split_once('?') {
+            Some((authority, query)) => (authority, Some(query)),
+            None => (rest, None),
+        };
+
+        let (model, host_and_port) = match authority.split_once('@') {
+            Some((model, host_and_port)) => (model.to_string(), host_and_port),
+            None => (String::new(), authority),
+        };
+
+        if host_and_port.is_empty() {
+            return Err(anyhow!("generator spec '{}' is missing a host", spec));
+        }
+
+        let (host, port) = match host_and_port.rsplit_once(':') {
+            Some((host, port_str)) => (
+                host.to_string(),
+                port_str
+                    .parse::<u16>()
+                    .map_err(|_| anyhow!("invalid port '{}' in generator spec '{}'", port_str, spec))?,
+            ),
+            None => (host_and_port.to_string(), DEFAULT_PORT),
+        };
+
+        let mut params = HashMap::new();
+        let mut context_window = DEFAULT_CONTEXT_WINDOW;
+
+        if let Some(query) = query {
+            for pair in query.split('&').filter(|p| !p.is_empty()) {
+                let (key, value) = pair
+                    .split_once('=')
+                    .ok_or_else(|| anyhow!("invalid query parameter '{}' in generator spec '{}'", pair, spec))?;
+
+                if key == "ctx" {
+                    context_window = value
+                        .parse::<u32>()
+                        .map_err(|_| anyhow!("invalid context window '{}' in generator spec '{}'", value, spec))?;
+                } else {
+                    params.insert(key.to_string(), value.to_string());
+                }
+            }
+        }
+
+        Ok(Self {
+            provider: provider.to_string(),
+            model,
+            host,
+            port,
+            context_window,
+            params,
+        })
+    }
+
+    // builds `ChatOptions` for this spec, threading `self.params` (e.g.
+    // "temperature", "top_p") into `generation_params` so per-model
+    // sampling parameters attached to the spec actually reach the client.
+    pub fn chat_options(
+        &self,
+        system_prompt: String,
+        prompt: String,
+        history: Vec<Message>,
+    ) -> ChatOptions {
+        ChatOptions::new(system_prompt, prompt, history).with_generation_params(self.params.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_provider_model_host_port_and_params() {
+        let spec =
+            GeneratorSpec::parse("openai://gpt-4o@api.example.com:443?ctx=128000&temperature=0.2")
+                .unwrap();
+
+        assert_eq!(spec.provider, "openai");
+        assert_eq!(spec.model, "gpt-4o");
+        assert_eq!(spec.host, "api.example.com");
+        assert_eq!(spec.port, 443);
+        assert_eq!(spec.context_window, 128000);
+        assert_eq!(spec.params.get("temperature"), Some(&"0.2".to_string()));
+    }
+
+    #[test]
+    fn defaults_port_and_context_window_when_omitted() {
+        let spec = GeneratorSpec::parse("ollama://llama3@localhost").unwrap();
+
+        assert_eq!(spec.model, "llama3");
+        assert_eq!(spec.host, "localhost");
+        assert_eq!(spec.port, DEFAULT_PORT);
+        assert_eq!(spec.context_window, DEFAULT_CONTEXT_WINDOW);
+        assert!(spec.params.is_empty());
+    }
+
+    #[test]
+    fn allows_missing_model() {
+        let spec = GeneratorSpec::parse("ollama://localhost:11434").unwrap();
+
+        assert!(spec.model.is_empty());
+        assert_eq!(spec.host, "localhost");
+        assert_eq!(spec.port, 11434);
+    }
+
+    #[test]
+    fn rejects_missing_scheme() {
+        assert!(GeneratorSpec::parse("gpt-4o@api.example.com").is_err());
+    }
+
+    #[test]
+    fn rejects_empty_provider() {
+        assert!(GeneratorSpec::parse("://api.example.com").is_err());
+    }
+
+    #[test]
+    fn rejects_missing_host() {
+        assert!(GeneratorSpec::parse("openai://gpt-4o@").is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_port() {
+        assert!(GeneratorSpec::parse("openai://gpt-4o@api.example.com:notaport").is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_context_window() {
+        assert!(GeneratorSpec::parse("openai://gpt-4o@api.example.com?ctx=notanumber").is_err());
+    }
+
+    #[test]
+    fn collects_multiple_extra_params() {
+        let spec =
+            GeneratorSpec::parse("openai://gpt-4o@api.example.com?temperature=0.2&top_p=0.9")
+                .unwrap();
+
+        assert_eq!(spec.params.get("temperature"), Some(&"0.2".to_string()));
+        assert_eq!(spec.params.get("top_p"), Some(&"0.9".to_string()));
+        assert_eq!(spec.context_window, DEFAULT_CONTEXT_WINDOW);
+    }
+
+    #[test]
+    fn chat_options_threads_params_as_generation_params() {
+        let spec = GeneratorSpec::parse("openai://gpt-4o@api.example.com?temperature=0.2").unwrap();
+        let options = spec.chat_options("system".to_string(), "prompt".to_string(), Vec::new());
+
+        assert_eq!(
+            options.generation_params.get("temperature"),
+            Some(&"0.2".to_string())
+        );
+    }
+}
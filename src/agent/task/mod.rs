@@ -1,12 +1,25 @@
 use anyhow::Result;
 
+use super::generator::{ChatOptions, Client, Message};
 use super::namespaces::Namespace;
+use super::state::SharedState;
 
 pub(crate) mod tasklet;
 pub(crate) mod variables;
 
 // TODO: comment the shit out of everything.
 
+// how a task wants its conversation history trimmed once it crosses
+// `Task::max_history_visibility`.
+#[derive(Debug, Clone, Copy)]
+pub enum HistoryPolicy {
+    // drop the oldest messages outright (current, default behavior).
+    Truncate,
+    // fold everything older than `keep_recent` messages into a single
+    // synthetic summary instead of dropping it.
+    Summarize { keep_recent: usize },
+}
+
 pub trait Task: std::fmt::Debug {
     fn to_system_prompt(&self) -> Result<String>;
     fn to_prompt(&self) -> Result<String>;
@@ -16,6 +29,10 @@ pub trait Task: std::fmt::Debug {
         50
     }
 
+    fn history_policy(&self) -> HistoryPolicy {
+        HistoryPolicy::Truncate
+    }
+
     fn guidance(&self) -> Result<Vec<String>> {
         self.base_guidance()
     }
@@ -33,3 +50,67 @@ pub trait Task: std::fmt::Debug {
             .collect())
     }
 }
+
+// compacts `history` once it grows past `task.max_history_visibility()`,
+// according to `task.history_policy()`. under `Truncate` the oldest messages
+// are simply dropped down to the limit; under `Summarize`, everything older
+// than `keep_recent` is fed back through `client` with a summarization
+// prompt and replaced by a single `Message::Feedback` entry, so long task
+// chains stay coherent instead of losing context outright. the result then
+// holds `keep_recent + 1` entries, which is only within the limit when
+// `keep_recent` is below it. messages within the kept window are left
+// untouched, so any `Invocation` they reference survives as-is.
+pub async fn apply_history_policy(
+    task: &dyn Task,
+    client: &dyn Client,
+    state: SharedState,
+    history: &mut Vec<Message>,
+) -> Result<()> {
+    let limit = task.max_history_visibility() as usize;
+    if history.len() <= limit {
+        return Ok(());
+    }
+
+    match task.history_policy() {
+        HistoryPolicy::Truncate => {
+            let overflow = history.len() - limit;
+            history.drain(0..overflow);
+        }
+        HistoryPolicy::Summarize { keep_recent } => {
+            let keep_recent = keep_recent.min(history.len());
+            let split = history.len() - keep_recent;
+            if split == 0 {
+                // everything is within `keep_recent`: nothing to fold into
+                // a summary, so don't spend a model round-trip on an empty
+                // transcript (and don't add an entry to a history that is
+                // already over `limit`).
+                return Ok(());
+            }
+            let to_summarize: Vec<Message> = history.drain(0..split).collect();
+
+            let transcript = to_summarize
+                .iter()
+                .map(|m| m.to_string())
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            let options = ChatOptions::new(
+                "You are compacting an agent's older conversation turns into a single \
+                 summary so they stop consuming context window space."
+                    .to_string(),
+                format!(
+                    "Summarize the following conversation history, preserving any facts, \
+                     decisions or tool results a future turn might still need:\n\n{}",
+                    transcript
+                ),
+                Vec::new(),
+            );
+
+            let (summary, _) = client.chat(state, &options).await?;
+
+            history.insert(0, Message::Feedback(summary, None));
+        }
+    }
+
+    Ok(())
+}